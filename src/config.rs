@@ -0,0 +1,28 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// A `--config` file listing several archiving targets, so one invocation can back
+/// up several Mastodon accounts (possibly on different instances) at once.
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    pub clients: Vec<ClientConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClientConfig {
+    pub host: String,
+    pub access_token: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub account_id: Option<String>,
+    pub file: String,
+}
+
+/// Loads a `Config` from a TOML file.
+pub fn load(path: &Path) -> Result<Config> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read config file {:?}", path))?;
+    toml::from_str(&contents).with_context(|| format!("failed to parse config file {:?}", path))
+}
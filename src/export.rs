@@ -0,0 +1,235 @@
+use std::io::Write;
+
+use anyhow::Result;
+use clap::ValueEnum;
+
+/// Which shape to write the archived statuses in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// The tool's native format: a pretty-printed JSON array of Mastodon status objects.
+    Json,
+    /// An ActivityStreams `OrderedCollection`, matching the shape of Mastodon's own export.
+    Outbox,
+    /// A flat CSV of the fields most people want out of an archive.
+    Csv,
+    /// A single browsable HTML page.
+    Html,
+}
+
+/// Transforms and writes out a finished, sorted list of statuses. One impl per
+/// `Format`, so new formats can be added without touching the others.
+pub trait Exporter {
+    fn write(&self, statuses: &[serde_json::Value], writer: &mut dyn Write) -> Result<()>;
+}
+
+pub fn exporter_for(format: Format) -> Box<dyn Exporter> {
+    match format {
+        Format::Json => Box::new(JsonExporter),
+        Format::Outbox => Box::new(OutboxExporter),
+        Format::Csv => Box::new(CsvExporter),
+        Format::Html => Box::new(HtmlExporter),
+    }
+}
+
+struct JsonExporter;
+
+impl Exporter for JsonExporter {
+    fn write(&self, statuses: &[serde_json::Value], writer: &mut dyn Write) -> Result<()> {
+        let output = serde_json::Value::Array(statuses.to_vec());
+        serde_json::to_writer_pretty(writer, &output)?;
+        Ok(())
+    }
+}
+
+struct OutboxExporter;
+
+impl Exporter for OutboxExporter {
+    fn write(&self, statuses: &[serde_json::Value], writer: &mut dyn Write) -> Result<()> {
+        let outbox = serde_json::json!({
+            "@context": "https://www.w3.org/ns/activitystreams",
+            "type": "OrderedCollection",
+            "totalItems": statuses.len(),
+            "orderedItems": statuses,
+        });
+        serde_json::to_writer_pretty(writer, &outbox)?;
+        Ok(())
+    }
+}
+
+struct CsvExporter;
+
+impl Exporter for CsvExporter {
+    fn write(&self, statuses: &[serde_json::Value], writer: &mut dyn Write) -> Result<()> {
+        let mut csv_writer = csv::Writer::from_writer(writer);
+        csv_writer.write_record([
+            "id",
+            "created_at",
+            "url",
+            "visibility",
+            "content",
+            "is_reply",
+            "is_boost",
+        ])?;
+        for status in statuses {
+            csv_writer.write_record([
+                status["id"].as_str().unwrap_or_default(),
+                status["created_at"].as_str().unwrap_or_default(),
+                status["url"].as_str().unwrap_or_default(),
+                status["visibility"].as_str().unwrap_or_default(),
+                &strip_html_tags(status["content"].as_str().unwrap_or_default()),
+                &(!status["in_reply_to_id"].is_null()).to_string(),
+                &(!status["reblog"].is_null()).to_string(),
+            ])?;
+        }
+        csv_writer.flush()?;
+        Ok(())
+    }
+}
+
+struct HtmlExporter;
+
+impl Exporter for HtmlExporter {
+    fn write(&self, statuses: &[serde_json::Value], writer: &mut dyn Write) -> Result<()> {
+        writeln!(writer, "<!DOCTYPE html>")?;
+        writeln!(writer, "<html lang=\"en\"><head><meta charset=\"utf-8\">")?;
+        writeln!(writer, "<title>Archived statuses</title></head><body>")?;
+        for status in statuses {
+            let created_at = status["created_at"].as_str().unwrap_or_default();
+            let url = status["url"].as_str().unwrap_or_default();
+            let content = status["content"].as_str().unwrap_or_default();
+
+            writeln!(writer, "<article>")?;
+            writeln!(
+                writer,
+                "<time datetime=\"{}\"><a href=\"{}\">{}</a></time>",
+                html_escape(created_at),
+                html_escape(url),
+                html_escape(created_at)
+            )?;
+            // Deliberately not `html_escape`d, unlike the siblings above: `content` is
+            // itself an HTML fragment (Mastodon sanitizes it server-side before
+            // federating), and escaping it here would show raw tags instead of the
+            // formatted post. This trusts the instance's sanitizer to have stripped
+            // anything unsafe out of federated content.
+            writeln!(writer, "<div class=\"content\">{}</div>", content)?;
+
+            if let Some(attachments) = status["media_attachments"].as_array() {
+                for attachment in attachments {
+                    if let Some(attachment_url) = attachment["url"].as_str() {
+                        writeln!(
+                            writer,
+                            "<p><a href=\"{}\">{}</a></p>",
+                            html_escape(attachment_url),
+                            html_escape(attachment_url)
+                        )?;
+                    }
+                }
+            }
+            writeln!(writer, "</article>")?;
+        }
+        writeln!(writer, "</body></html>")?;
+        Ok(())
+    }
+}
+
+/// Removes HTML tags, keeping the text between them. HTML entities such as `&amp;`
+/// are left encoded. Mastodon's `content` field is always a small, well-formed HTML
+/// fragment, so this doesn't need a full parser.
+fn strip_html_tags(html: &str) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => result.push(c),
+            _ => {}
+        }
+    }
+    result
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_html_tags_removes_tags_but_keeps_text() {
+        assert_eq!(
+            strip_html_tags("<p>Hello <b>world</b>!</p>"),
+            "Hello world!"
+        );
+    }
+
+    #[test]
+    fn strip_html_tags_passes_through_plain_text() {
+        assert_eq!(strip_html_tags("no tags here"), "no tags here");
+    }
+
+    fn sample_status() -> serde_json::Value {
+        serde_json::json!({
+            "id": "1",
+            "created_at": "2026-01-01T00:00:00.000Z",
+            "url": "https://example.social/@alice/1",
+            "visibility": "public",
+            "content": "<p>hello &amp; welcome</p>",
+            "in_reply_to_id": null,
+            "reblog": null,
+            "media_attachments": [
+                {"url": "https://example.social/media/1.jpg"}
+            ],
+        })
+    }
+
+    #[test]
+    fn outbox_exporter_wraps_statuses_in_an_ordered_collection() {
+        let statuses = vec![sample_status()];
+        let mut out = Vec::new();
+        OutboxExporter.write(&statuses, &mut out).unwrap();
+
+        let value: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(value["@context"], "https://www.w3.org/ns/activitystreams");
+        assert_eq!(value["type"], "OrderedCollection");
+        assert_eq!(value["totalItems"], 1);
+        assert_eq!(value["orderedItems"][0]["id"], "1");
+    }
+
+    #[test]
+    fn csv_exporter_writes_the_expected_header_and_columns() {
+        let statuses = vec![sample_status()];
+        let mut out = Vec::new();
+        CsvExporter.write(&statuses, &mut out).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            "id,created_at,url,visibility,content,is_reply,is_boost"
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            "1,2026-01-01T00:00:00.000Z,https://example.social/@alice/1,public,hello &amp; welcome,false,false"
+        );
+    }
+
+    #[test]
+    fn html_exporter_escapes_metadata_but_not_content() {
+        let mut status = sample_status();
+        status["url"] = serde_json::json!("https://example.social/@alice/1?a=1&b=2");
+        let statuses = vec![status];
+        let mut out = Vec::new();
+        HtmlExporter.write(&statuses, &mut out).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("<div class=\"content\"><p>hello &amp; welcome</p></div>"));
+        assert!(text.contains("href=\"https://example.social/@alice/1?a=1&amp;b=2\""));
+        assert!(text.contains("href=\"https://example.social/media/1.jpg\""));
+    }
+}
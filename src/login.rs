@@ -0,0 +1,187 @@
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+const REDIRECT_URI: &str = "urn:ietf:wg:oauth:2.0:oob";
+const SCOPES: &str = "read:statuses";
+const CODE_VERIFIER_LEN: usize = 64;
+
+/// Runs the interactive `login` subcommand: registers an app, walks the user through
+/// the OAuth 2.0 authorization-code grant with PKCE, and persists the resulting
+/// access token so future runs pick it up automatically.
+pub fn run(host: &str) -> Result<()> {
+    let client = reqwest::blocking::Client::new();
+    let (client_id, client_secret) = register_app(&client, host)?;
+
+    let code_verifier = generate_code_verifier();
+    let code_challenge = code_challenge_for(&code_verifier);
+
+    let authorize_url = format!(
+        "{host}/oauth/authorize?response_type=code&client_id={client_id}&redirect_uri={redirect_uri}&scope={scope}&code_challenge={challenge}&code_challenge_method=S256",
+        host = host,
+        client_id = client_id,
+        redirect_uri = REDIRECT_URI,
+        scope = SCOPES,
+        challenge = code_challenge,
+    );
+
+    println!("Open this URL in a browser and authorize the app:\n");
+    println!("{}\n", authorize_url);
+    println!("Paste the authorization code here:");
+
+    let mut code = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut code)
+        .context("failed to read authorization code from stdin")?;
+    let code = code.trim();
+
+    let access_token = exchange_code(&client, host, &client_id, &client_secret, &code_verifier, code)?;
+
+    save_credentials(host, &access_token)?;
+    println!("Login successful. The access token has been saved to {:?}.", credentials_path()?);
+
+    Ok(())
+}
+
+fn register_app(client: &reqwest::blocking::Client, host: &str) -> Result<(String, String)> {
+    let resp: serde_json::Value = client
+        .post(format!("{}/api/v1/apps", host))
+        .form(&[
+            ("client_name", "get-my-history"),
+            ("redirect_uris", REDIRECT_URI),
+            ("scopes", SCOPES),
+        ])
+        .send()?
+        .error_for_status()?
+        .json()?;
+
+    let client_id = resp["client_id"]
+        .as_str()
+        .context("client_id not found in app registration response")?
+        .to_string();
+    let client_secret = resp["client_secret"]
+        .as_str()
+        .context("client_secret not found in app registration response")?
+        .to_string();
+
+    Ok((client_id, client_secret))
+}
+
+fn exchange_code(
+    client: &reqwest::blocking::Client,
+    host: &str,
+    client_id: &str,
+    client_secret: &str,
+    code_verifier: &str,
+    code: &str,
+) -> Result<String> {
+    let resp: serde_json::Value = client
+        .post(format!("{}/oauth/token", host))
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", REDIRECT_URI),
+            ("client_id", client_id),
+            ("client_secret", client_secret),
+            ("code_verifier", code_verifier),
+        ])
+        .send()?
+        .error_for_status()?
+        .json()?;
+
+    Ok(resp["access_token"]
+        .as_str()
+        .context("access_token not found in token response")?
+        .to_string())
+}
+
+fn generate_code_verifier() -> String {
+    const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-._~";
+    let mut rng = rand::thread_rng();
+    (0..CODE_VERIFIER_LEN)
+        .map(|_| CHARSET[rng.gen_range(0..CHARSET.len())] as char)
+        .collect()
+}
+
+fn code_challenge_for(code_verifier: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(code_verifier.as_bytes());
+    URL_SAFE_NO_PAD.encode(hasher.finalize())
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct StoredCredential {
+    host: String,
+    access_token: String,
+}
+
+fn credentials_path() -> Result<PathBuf> {
+    let dirs = directories::ProjectDirs::from("", "", "get-my-history")
+        .context("could not determine config directory")?;
+    Ok(dirs.config_dir().join("credentials.json"))
+}
+
+fn load_all_credentials() -> Result<Vec<StoredCredential>> {
+    let path = credentials_path()?;
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let contents = fs::read_to_string(&path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// Looks up a previously saved access token for `host`, if `login` has been run before.
+pub fn load_credentials(host: &str) -> Result<Option<String>> {
+    Ok(load_all_credentials()?
+        .into_iter()
+        .find(|c| c.host == host)
+        .map(|c| c.access_token))
+}
+
+fn save_credentials(host: &str, access_token: &str) -> Result<()> {
+    let mut credentials = load_all_credentials()?;
+    credentials.retain(|c| c.host != host);
+    credentials.push(StoredCredential {
+        host: host.to_owned(),
+        access_token: access_token.to_owned(),
+    });
+
+    let path = credentials_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = open_restricted(&path)?;
+    file.write_all(serde_json::to_string_pretty(&credentials)?.as_bytes())?;
+
+    Ok(())
+}
+
+/// Opens `credentials.json` for writing, creating it pre-restricted to the owner
+/// only. It holds a long-lived access token, so - unlike `fs::write`, which creates
+/// the file under the process umask and would leave a window where it's
+/// world/group-readable - the permissions must be set atomically at creation time.
+#[cfg(unix)]
+fn open_restricted(path: &Path) -> Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    Ok(fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?)
+}
+
+#[cfg(not(unix))]
+fn open_restricted(path: &Path) -> Result<fs::File> {
+    Ok(fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?)
+}
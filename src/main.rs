@@ -1,9 +1,24 @@
-use std::{cmp::Ordering, fs::File, io::BufWriter};
+use std::{
+    cmp::Ordering,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{Context, Result};
-use clap::Parser;
+use chrono::Utc;
+use clap::{Parser, Subcommand};
 use reqwest::header::HeaderValue;
 
+mod config;
+mod export;
+mod login;
+mod media;
+mod ratelimit;
+mod store;
+
+use export::Format;
+
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
@@ -25,18 +40,63 @@ struct Cli {
     #[arg(short = 'u', long)]
     update_in_place: bool,
 
+    /// Download every media attachment into this directory, content-addressed by
+    /// SHA-256, and rewrite attachment URLs to point at the local copy.
+    #[arg(long)]
+    download_media: Option<PathBuf>,
+
+    /// How many times to retry a request after a 429 or a transient server error
+    /// before giving up.
+    #[arg(long, default_value_t = 5)]
+    max_retries: u32,
+
+    /// Archive several accounts in one run, as described in a TOML config file,
+    /// instead of the single target described by the other flags.
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// The shape to write the archive in.
+    #[arg(long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+
+    /// Store the archive as newline-delimited JSON instead of a JSON array, so that
+    /// --update-in-place can append new statuses without rewriting the file.
+    /// Only valid together with --format json.
+    #[arg(long)]
+    jsonl: bool,
+
     file: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-fn get_access_token(args: &Cli) -> Result<String> {
-    if let Some(token) = &args.access_token {
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Interactively log in via OAuth 2.0 authorization-code + PKCE and save the
+    /// resulting access token, so private statuses can be archived without a
+    /// pre-issued --access-token.
+    Login,
+}
+
+fn get_access_token(
+    host: &str,
+    access_token: &Option<String>,
+    client_id: &Option<String>,
+    client_secret: &Option<String>,
+) -> Result<String> {
+    if let Some(token) = access_token {
         return Ok(token.clone());
     }
 
-    if let (Some(client_id), Some(client_secret)) = (&args.client_id, &args.client_secret) {
+    if let Some(token) = login::load_credentials(host)? {
+        return Ok(token);
+    }
+
+    if let (Some(client_id), Some(client_secret)) = (client_id, client_secret) {
         let client = reqwest::blocking::Client::new();
         let resp = client
-            .post(format!("{}/oauth/token", args.host))
+            .post(format!("{}/oauth/token", host))
             .form(&[
                 ("client_id", client_id),
                 ("client_secret", client_secret),
@@ -53,7 +113,10 @@ fn get_access_token(args: &Cli) -> Result<String> {
             .to_string());
     }
 
-    anyhow::bail!("Either access_token or both client_id and client_secret must be provided")
+    anyhow::bail!(
+        "Either access_token, both client_id and client_secret, or a saved login \
+        (run the `login` subcommand first) must be provided"
+    )
 }
 
 fn api_get(host: &str, access_token: &str, path: &str) -> Result<serde_json::Value> {
@@ -103,12 +166,70 @@ fn parse_link(header: &HeaderValue, dir: &str) -> Option<String> {
     None
 }
 
+/// Sends one page request, transparently waiting out rate limits and retrying
+/// transient errors, up to `max_retries` times.
+fn get_page(
+    client: &reqwest::blocking::Client,
+    full_url: &str,
+    params: &[(&str, &str)],
+    access_token: &str,
+    max_retries: u32,
+) -> Result<reqwest::blocking::Response> {
+    let mut attempt = 0;
+    loop {
+        log::info!("getting {}", full_url);
+        let resp = client
+            .get(full_url)
+            .query(params)
+            .bearer_auth(access_token)
+            .send()?;
+
+        if resp.status().is_success() {
+            if ratelimit::remaining_requests(&resp) == Some(0) {
+                if let Some(reset) = ratelimit::reset_at(&resp) {
+                    let wait = ratelimit::duration_until(reset, Utc::now());
+                    log::info!("rate limit exhausted, sleeping for {:?}", wait);
+                    std::thread::sleep(wait);
+                }
+            }
+            return Ok(resp);
+        }
+
+        if attempt >= max_retries {
+            return Err(resp.error_for_status().unwrap_err().into());
+        }
+
+        let wait = if resp.status().as_u16() == 429 {
+            ratelimit::retry_after(&resp)
+                .or_else(|| ratelimit::reset_at(&resp).map(|reset| ratelimit::duration_until(reset, Utc::now())))
+                .unwrap_or_else(|| ratelimit::backoff_delay(attempt))
+        } else if resp.status().is_server_error() {
+            ratelimit::backoff_delay(attempt)
+        } else {
+            return Err(resp.error_for_status().unwrap_err().into());
+        };
+
+        log::warn!(
+            "got {} from {}, retrying in {:?} (attempt {}/{})",
+            resp.status(),
+            full_url,
+            wait,
+            attempt + 1,
+            max_retries
+        );
+        std::thread::sleep(wait);
+        attempt += 1;
+    }
+}
+
 fn get_statuses(
     host: &str,
     access_token: &str,
     account_id: &str,
     min_id: Option<&str>,
+    max_retries: u32,
 ) -> Result<Vec<serde_json::Value>> {
+    let client = reqwest::blocking::Client::new();
     let mut has_more = true;
 
     let mut url = format!("/api/v1/accounts/{}/statuses", account_id);
@@ -126,15 +247,8 @@ fn get_statuses(
     let mut result: Vec<serde_json::Value> = vec![];
 
     while has_more {
-        let client = reqwest::blocking::Client::new();
         let full_url = format!("{}{}", host, &url);
-        log::info!("getting {}", full_url);
-        let resp = client
-            .get(&full_url)
-            .query(&params)
-            .bearer_auth(access_token)
-            .send()?
-            .error_for_status()?;
+        let resp = get_page(&client, &full_url, &params, access_token, max_retries)?;
 
         has_more = false;
 
@@ -159,31 +273,59 @@ fn compare_key(key: &str, a: &serde_json::Value, b: &serde_json::Value) -> Order
     a[key].as_str().unwrap().cmp(b[key].as_str().unwrap())
 }
 
-fn main() -> Result<()> {
-    let _ = dotenvy::dotenv();
-
-    env_logger::init();
-
-    let args = Cli::parse();
-    let access_token = get_access_token(&args)?;
+/// One account to fetch-merge-sort-write, whether it came from CLI flags or from
+/// one entry of a `--config` file.
+struct Target {
+    host: String,
+    access_token: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    account_id: Option<String>,
+    file: Option<String>,
+    update_in_place: bool,
+    download_media: Option<PathBuf>,
+    format: Format,
+    jsonl: bool,
+}
 
-    let account_id = if let Some(ref account_id) = args.account_id {
-        account_id
+fn archive_target(target: &Target, max_retries: u32) -> Result<()> {
+    anyhow::ensure!(
+        !target.jsonl || target.format == Format::Json,
+        "--jsonl can only be combined with --format json"
+    );
+
+    let access_token = get_access_token(
+        &target.host,
+        &target.access_token,
+        &target.client_id,
+        &target.client_secret,
+    )?;
+
+    let account_id = if let Some(ref account_id) = target.account_id {
+        account_id.clone()
     } else {
-        &get_account_id(&args.host, &access_token)?
+        get_account_id(&target.host, &access_token)?
     };
 
+    if target.update_in_place && target.format == Format::Json {
+        return archive_incrementally(target, &access_token, &account_id, max_retries);
+    }
+
     let mut statuses: Vec<serde_json::Value> = vec![];
 
-    let max_id = if let Some(ref filename) = args.file {
-        // TODO(miikka) Give a good error message if args.file is not set.
-        let f = File::open(filename)?;
-        let v: serde_json::Value = serde_json::from_reader(f)?;
-        statuses = v.as_array().unwrap().clone();
-        statuses.sort_by(|a, b| compare_key("created_at", b, a));
-        statuses
-            .first()
-            .map(|s| s["id"].as_str().unwrap().to_owned())
+    let max_id = if let Some(ref filename) = target.file {
+        // TODO(miikka) Give a good error message if target.file is not set.
+        if Path::new(filename).exists() {
+            let f = File::open(filename)?;
+            let v: serde_json::Value = serde_json::from_reader(f)?;
+            statuses = v.as_array().unwrap().clone();
+            statuses.sort_by(|a, b| compare_key("created_at", b, a));
+            statuses
+                .first()
+                .map(|s| s["id"].as_str().unwrap().to_owned())
+        } else {
+            None
+        }
     } else {
         None
     };
@@ -191,21 +333,124 @@ fn main() -> Result<()> {
     log::info!("max ID: {:?}", max_id);
 
     statuses.extend(get_statuses(
-        &args.host,
+        &target.host,
         &access_token,
-        account_id,
+        &account_id,
         max_id.as_deref(),
+        max_retries,
     )?);
     statuses.sort_by(|a, b| compare_key("created_at", a, b));
-    let output = serde_json::Value::Array(statuses);
 
-    let writer: Box<dyn std::io::Write> = if args.update_in_place {
-        Box::new(BufWriter::new(File::create(args.file.unwrap())?))
+    if let Some(ref dir) = target.download_media {
+        let client = reqwest::blocking::Client::new();
+        media::archive_attachments(&client, dir, &mut statuses)?;
+    }
+
+    let mut writer: Box<dyn std::io::Write> = if target.update_in_place {
+        Box::new(BufWriter::new(File::create(
+            target.file.as_ref().context("--file is required with --update-in-place")?,
+        )?))
     } else {
         Box::new(std::io::stdout())
     };
 
-    serde_json::to_writer_pretty(writer, &output)?;
+    export::exporter_for(target.format).write(&statuses, &mut writer)?;
 
     Ok(())
 }
+
+/// The `--update-in-place` + native JSON/JSONL path: instead of loading the whole
+/// existing archive into memory, find the newest status already on disk by reading
+/// only the tail of the file, fetch everything newer, and append just that to the
+/// file in place.
+fn archive_incrementally(
+    target: &Target,
+    access_token: &str,
+    account_id: &str,
+    max_retries: u32,
+) -> Result<()> {
+    let path = target
+        .file
+        .as_ref()
+        .context("--file is required with --update-in-place")?;
+    let path = Path::new(path);
+
+    let max_id = store::newest_id(path, target.jsonl)?;
+    log::info!("max ID: {:?}", max_id);
+
+    let mut new_statuses = get_statuses(
+        &target.host,
+        access_token,
+        account_id,
+        max_id.as_deref(),
+        max_retries,
+    )?;
+    new_statuses.sort_by(|a, b| compare_key("created_at", a, b));
+
+    if let Some(ref dir) = target.download_media {
+        let client = reqwest::blocking::Client::new();
+        media::archive_attachments(&client, dir, &mut new_statuses)?;
+    }
+
+    store::append(path, &new_statuses, target.jsonl)
+}
+
+fn main() -> Result<()> {
+    let _ = dotenvy::dotenv();
+
+    env_logger::init();
+
+    let args = Cli::parse();
+
+    if matches!(args.command, Some(Command::Login)) {
+        return login::run(&args.host);
+    }
+
+    if let Some(ref config_path) = args.config {
+        let config = config::load(config_path)?;
+        let total = config.clients.len();
+        let mut failed_hosts = vec![];
+        for client in config.clients {
+            let host = client.host.clone();
+            let target = Target {
+                host: client.host,
+                access_token: client.access_token,
+                client_id: client.client_id,
+                client_secret: client.client_secret,
+                account_id: client.account_id,
+                file: Some(client.file),
+                update_in_place: true,
+                download_media: args.download_media.clone(),
+                format: args.format,
+                jsonl: args.jsonl,
+            };
+            if let Err(e) = archive_target(&target, args.max_retries) {
+                log::error!("failed to archive {}: {:#}", host, e);
+                failed_hosts.push(host);
+            }
+        }
+
+        anyhow::ensure!(
+            failed_hosts.is_empty(),
+            "failed to archive {} of {} target(s): {}",
+            failed_hosts.len(),
+            total,
+            failed_hosts.join(", ")
+        );
+        return Ok(());
+    }
+
+    let target = Target {
+        host: args.host,
+        access_token: args.access_token,
+        client_id: args.client_id,
+        client_secret: args.client_secret,
+        account_id: args.account_id,
+        file: args.file,
+        update_in_place: args.update_in_place,
+        download_media: args.download_media,
+        format: args.format,
+        jsonl: args.jsonl,
+    };
+    archive_target(&target, args.max_retries)
+}
@@ -0,0 +1,192 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+
+/// Downloads every media attachment referenced by `statuses` into `dir`, using a
+/// content-addressed layout (`<first two hex chars>/<full hash>.<ext>`), and rewrites
+/// each attachment's `url` to point at the local copy. The original remote URL is
+/// preserved under a new `original_url` key so the archive stays self-describing.
+pub fn archive_attachments(
+    client: &reqwest::blocking::Client,
+    dir: &Path,
+    statuses: &mut [serde_json::Value],
+) -> Result<()> {
+    for status in statuses.iter_mut() {
+        let Some(attachments) = status["media_attachments"].as_array_mut() else {
+            continue;
+        };
+        for attachment in attachments.iter_mut() {
+            archive_attachment(client, dir, attachment)?;
+        }
+    }
+    Ok(())
+}
+
+fn archive_attachment(
+    client: &reqwest::blocking::Client,
+    dir: &Path,
+    attachment: &mut serde_json::Value,
+) -> Result<()> {
+    if already_archived(attachment) {
+        // `url` now points at our local content-addressed copy, not the remote
+        // attachment, so there's nothing to (re-)download.
+        return Ok(());
+    }
+
+    let Some(url) = attachment["url"].as_str().map(str::to_owned) else {
+        return Ok(());
+    };
+    let kind = attachment["type"].as_str().unwrap_or("unknown");
+
+    let resp = client
+        .get(&url)
+        .send()?
+        .error_for_status()
+        .with_context(|| format!("failed to download media attachment {}", url))?;
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned);
+    let bytes = resp.bytes()?;
+
+    let hash = sha256_hex(&bytes);
+    let ext = extension_for(kind, content_type.as_deref(), &url);
+    let relative = content_addressed_path(&hash, &ext);
+    let full_path = dir.join(&relative);
+
+    if !full_path.exists() {
+        if let Some(parent) = full_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&full_path, &bytes)
+            .with_context(|| format!("failed to write media attachment to {:?}", full_path))?;
+    }
+
+    attachment["original_url"] = serde_json::Value::String(url);
+    attachment["url"] = serde_json::Value::String(relative.to_string_lossy().into_owned());
+
+    Ok(())
+}
+
+/// Whether `attachment` was already rewritten by a previous `--download-media` run,
+/// so re-running the archiver on an already-merged archive doesn't try to
+/// re-download (or treat its own local path as a remote URL).
+fn already_archived(attachment: &serde_json::Value) -> bool {
+    !attachment["original_url"].is_null()
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    format!("{:x}", hasher.finalize())
+}
+
+fn content_addressed_path(hash: &str, ext: &str) -> PathBuf {
+    let (prefix, _) = hash.split_at(2);
+    PathBuf::from(prefix).join(format!("{}.{}", hash, ext))
+}
+
+/// Infers a file extension for an attachment, preferring the Content-Type header,
+/// then the extension already present in the remote URL, then a default for the
+/// attachment's Mastodon `type`.
+fn extension_for(kind: &str, content_type: Option<&str>, url: &str) -> String {
+    if let Some(ext) = content_type.and_then(extension_for_mime) {
+        return ext.to_owned();
+    }
+    if let Some(ext) = Path::new(url).extension().and_then(|e| e.to_str()) {
+        return ext.to_owned();
+    }
+    default_extension_for_type(kind).to_owned()
+}
+
+fn extension_for_mime(content_type: &str) -> Option<&'static str> {
+    match content_type.split(';').next().unwrap_or("").trim() {
+        "image/jpeg" => Some("jpg"),
+        "image/png" => Some("png"),
+        "image/gif" => Some("gif"),
+        "image/webp" => Some("webp"),
+        "video/mp4" => Some("mp4"),
+        "video/webm" => Some("webm"),
+        "audio/mpeg" => Some("mp3"),
+        "audio/ogg" => Some("ogg"),
+        _ => None,
+    }
+}
+
+fn default_extension_for_type(kind: &str) -> &'static str {
+    match kind {
+        "image" => "jpg",
+        "video" | "gifv" => "mp4",
+        "audio" => "mp3",
+        _ => "bin",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha256_hex_matches_known_digest() {
+        assert_eq!(
+            sha256_hex(b"hello world"),
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+    }
+
+    #[test]
+    fn already_archived_is_false_for_a_fresh_attachment() {
+        let attachment = serde_json::json!({
+            "type": "image",
+            "url": "https://example.com/original.jpg",
+        });
+        assert!(!already_archived(&attachment));
+    }
+
+    #[test]
+    fn already_archived_is_true_once_original_url_is_set() {
+        let attachment = serde_json::json!({
+            "type": "image",
+            "url": "ab/deadbeef.jpg",
+            "original_url": "https://example.com/original.jpg",
+        });
+        assert!(already_archived(&attachment));
+    }
+
+    #[test]
+    fn content_addressed_path_splits_first_two_chars() {
+        let path = content_addressed_path("abcdef0123", "jpg");
+        assert_eq!(path, PathBuf::from("ab").join("abcdef0123.jpg"));
+    }
+
+    #[test]
+    fn extension_for_prefers_content_type() {
+        assert_eq!(
+            extension_for("image", Some("image/png"), "https://example.com/foo.jpeg"),
+            "png"
+        );
+    }
+
+    #[test]
+    fn extension_for_falls_back_to_url_suffix() {
+        assert_eq!(extension_for("image", None, "https://example.com/foo.webp"), "webp");
+    }
+
+    #[test]
+    fn extension_for_falls_back_to_type_default() {
+        assert_eq!(extension_for("audio", None, "https://example.com/foo"), "mp3");
+    }
+
+    #[test]
+    fn same_bytes_hash_to_the_same_path() {
+        let a = sha256_hex(b"duplicate content");
+        let b = sha256_hex(b"duplicate content");
+        assert_eq!(
+            content_addressed_path(&a, "jpg"),
+            content_addressed_path(&b, "jpg")
+        );
+    }
+}
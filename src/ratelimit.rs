@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Reads Mastodon's `X-RateLimit-Remaining` header, if present.
+pub fn remaining_requests(resp: &reqwest::blocking::Response) -> Option<u32> {
+    resp.headers()
+        .get("x-ratelimit-remaining")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+}
+
+/// Reads Mastodon's `X-RateLimit-Reset` header, an ISO-8601 timestamp, if present.
+pub fn reset_at(resp: &reqwest::blocking::Response) -> Option<DateTime<Utc>> {
+    resp.headers()
+        .get("x-ratelimit-reset")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| DateTime::parse_from_rfc3339(v).ok())
+        .map(|v| v.with_timezone(&Utc))
+}
+
+/// Reads the `Retry-After` header (seconds), if present.
+pub fn retry_after(resp: &reqwest::blocking::Response) -> Option<Duration> {
+    resp.headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+/// How long to sleep so that `now` becomes `target`, or zero if `target` is already past.
+pub fn duration_until(target: DateTime<Utc>, now: DateTime<Utc>) -> Duration {
+    (target - now).to_std().unwrap_or(Duration::ZERO)
+}
+
+/// Exponential backoff for the given (zero-based) retry attempt: 1s, 2s, 4s, ...,
+/// capped at `MAX_BACKOFF`.
+pub fn backoff_delay(attempt: u32) -> Duration {
+    1u64.checked_shl(attempt)
+        .map(Duration::from_secs)
+        .unwrap_or(MAX_BACKOFF)
+        .min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duration_until_future_is_positive() {
+        let now = Utc::now();
+        let target = now + chrono::Duration::seconds(30);
+        assert_eq!(duration_until(target, now), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn duration_until_past_is_zero() {
+        let now = Utc::now();
+        let target = now - chrono::Duration::seconds(30);
+        assert_eq!(duration_until(target, now), Duration::ZERO);
+    }
+
+    #[test]
+    fn backoff_delay_doubles_then_caps() {
+        assert_eq!(backoff_delay(0), Duration::from_secs(1));
+        assert_eq!(backoff_delay(1), Duration::from_secs(2));
+        assert_eq!(backoff_delay(2), Duration::from_secs(4));
+        assert_eq!(backoff_delay(10), MAX_BACKOFF);
+    }
+}
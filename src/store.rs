@@ -0,0 +1,259 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+/// How much of the end of the file to read when looking for the last record.
+/// Statuses are a few KB at most, so this comfortably covers the newest one
+/// without reading the whole archive.
+const TAIL_SIZE: u64 = 64 * 1024;
+
+fn read_tail(path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(TAIL_SIZE);
+    file.seek(SeekFrom::Start(start))?;
+    let mut buf = Vec::with_capacity((len - start) as usize);
+    file.read_to_end(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// Finds the `id` of the newest status already on disk, to use as `since_id` for
+/// the next fetch. Statuses are stored sorted by `created_at` ascending, so this
+/// is whatever comes last in the file - found by reading only the tail of it,
+/// rather than the whole archive.
+pub fn newest_id(path: &Path, jsonl: bool) -> Result<Option<String>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let tail = read_tail(path)?;
+
+    let last_record = if jsonl {
+        tail.lines().rev().find(|line| !line.trim().is_empty())
+    } else {
+        // The tail looks like "...,{...}\n]" (or just "[...]" for a short file);
+        // the last top-level "{...}" before the closing "]" is the newest status.
+        let body = tail
+            .trim_end()
+            .strip_suffix(']')
+            .context("existing archive is not a JSON array")?;
+        last_top_level_object(body)
+    };
+
+    let Some(record) = last_record else {
+        return Ok(None);
+    };
+    let value: Value = serde_json::from_str(record)?;
+    Ok(value["id"].as_str().map(str::to_owned))
+}
+
+/// Finds the last balanced top-level `{...}` object in `body` (the contents of a
+/// JSON array, minus its brackets). A plain `rfind('{')`/`rfind('}')` pair isn't
+/// enough: statuses nest objects (`account`, `application`, `card`, ...) and
+/// arrays-of-objects (`mentions`), so the `{` closest to the end of the buffer
+/// usually belongs to a *nested* object, not the last array element. This tracks
+/// brace depth (while skipping over string contents, so braces inside quoted text
+/// don't count) to find the object whose closing brace returns depth to zero.
+fn last_top_level_object(body: &str) -> Option<&str> {
+    let bytes = body.as_bytes();
+    let mut depth: u32 = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut obj_start = None;
+    let mut last_obj = None;
+
+    for (i, &b) in bytes.iter().enumerate() {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match b {
+            b'"' => in_string = true,
+            b'{' => {
+                if depth == 0 {
+                    obj_start = Some(i);
+                }
+                depth += 1;
+            }
+            b'}' => {
+                // The tail buffer may start mid-object (we only read the last
+                // TAIL_SIZE bytes of a possibly much larger file); skip stray
+                // closing braces left over from whatever element precedes the
+                // first one we can see opening cleanly.
+                if depth == 0 {
+                    continue;
+                }
+                depth -= 1;
+                if depth == 0 {
+                    if let Some(start) = obj_start.take() {
+                        last_obj = Some(&body[start..=i]);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    last_obj
+}
+
+/// Appends `statuses` (already sorted, all newer than anything on disk) to the
+/// archive at `path`, without re-reading or re-serializing what's already there.
+pub fn append(path: &Path, statuses: &[Value], jsonl: bool) -> Result<()> {
+    if jsonl {
+        append_jsonl(path, statuses)
+    } else {
+        append_json_array(path, statuses)
+    }
+}
+
+fn append_jsonl(path: &Path, statuses: &[Value]) -> Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    for status in statuses {
+        serde_json::to_writer(&mut file, status)?;
+        writeln!(file)?;
+    }
+    Ok(())
+}
+
+fn append_json_array(path: &Path, statuses: &[Value]) -> Result<()> {
+    if !path.exists() {
+        let mut file = File::create(path)?;
+        serde_json::to_writer_pretty(&mut file, &Value::Array(statuses.to_vec()))?;
+        return Ok(());
+    }
+
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let len = file.metadata()?.len();
+
+    // Walk backwards from the end, past whitespace, to find the closing "]".
+    let mut pos = len;
+    let mut byte = [0u8; 1];
+    let bracket_pos = loop {
+        anyhow::ensure!(pos > 0, "existing archive is not a JSON array");
+        pos -= 1;
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut byte)?;
+        match byte[0] {
+            b']' => break pos,
+            b if b.is_ascii_whitespace() => continue,
+            _ => anyhow::bail!("existing archive does not end with ']'"),
+        }
+    };
+
+    // Keep walking backwards to see whether the array already has an element in it.
+    let mut has_existing_elements = false;
+    while pos > 0 {
+        pos -= 1;
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut byte)?;
+        match byte[0] {
+            b if b.is_ascii_whitespace() => continue,
+            b'[' => break,
+            _ => {
+                has_existing_elements = true;
+                break;
+            }
+        }
+    }
+
+    file.set_len(bracket_pos)?;
+    file.seek(SeekFrom::Start(bracket_pos))?;
+    let mut wrote_any = has_existing_elements;
+    for status in statuses {
+        if wrote_any {
+            write!(file, ",")?;
+        }
+        writeln!(file)?;
+        serde_json::to_writer_pretty(&mut file, status)?;
+        wrote_any = true;
+    }
+    write!(file, "\n]")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A status shaped like a real Mastodon one: a nested `account` object, a
+    /// `mentions` array of objects, and an `application` sub-object - so the last
+    /// `{` in the buffer does *not* belong to the status itself.
+    fn status_json(id: &str) -> String {
+        format!(
+            r#"{{"id":"{id}","created_at":"2026-01-0{id}T00:00:00.000Z","content":"hi","account":{{"id":"9","username":"alice"}},"mentions":[{{"id":"1","username":"bob"}},{{"id":"2","username":"carol"}}],"application":{{"name":"app","website":null}}}}"#,
+            id = id
+        )
+    }
+
+    #[test]
+    fn last_top_level_object_skips_nested_objects() {
+        let body = format!("{},{}", status_json("1"), status_json("2"));
+        let found = last_top_level_object(&body).expect("should find an object");
+        assert_eq!(found, status_json("2"));
+
+        let value: Value = serde_json::from_str(found).unwrap();
+        assert_eq!(value["id"], "2");
+    }
+
+    #[test]
+    fn last_top_level_object_ignores_braces_inside_strings() {
+        let tricky = r#"{"id":"1","content":"look at this: { \"not json\" }"}"#;
+        let body = format!("{},{}", tricky, status_json("2"));
+        let found = last_top_level_object(&body).expect("should find an object");
+        let value: Value = serde_json::from_str(found).unwrap();
+        assert_eq!(value["id"], "2");
+    }
+
+    #[test]
+    fn last_top_level_object_handles_single_element() {
+        let body = status_json("1");
+        let found = last_top_level_object(&body).expect("should find an object");
+        assert_eq!(found, status_json("1"));
+    }
+
+    #[test]
+    fn newest_id_reads_last_status_from_a_realistic_archive() {
+        let dir = std::env::temp_dir().join(format!(
+            "gmh-store-test-{}-{}",
+            std::process::id(),
+            "newest-id-json"
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("archive.json");
+        let contents = format!("[\n{},\n{}\n]", status_json("1"), status_json("2"));
+        std::fs::write(&path, contents).unwrap();
+
+        let id = newest_id(&path, false).unwrap();
+        assert_eq!(id, Some("2".to_owned()));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn newest_id_reads_last_status_from_jsonl() {
+        let dir = std::env::temp_dir().join(format!(
+            "gmh-store-test-{}-{}",
+            std::process::id(),
+            "newest-id-jsonl"
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("archive.jsonl");
+        let contents = format!("{}\n{}\n", status_json("1"), status_json("2"));
+        std::fs::write(&path, contents).unwrap();
+
+        let id = newest_id(&path, true).unwrap();
+        assert_eq!(id, Some("2".to_owned()));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}